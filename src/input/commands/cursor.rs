@@ -1,4 +1,5 @@
 use application::Application;
+use scribe::buffer::Position;
 
 pub fn move_up(app: &mut Application, _: &char) {
     app.workspace.current_buffer().unwrap().cursor.move_up();
@@ -23,3 +24,455 @@ pub fn move_to_start_of_line(app: &mut Application, _: &char) {
 pub fn move_to_end_of_line(app: &mut Application, _: &char) {
     app.workspace.current_buffer().unwrap().cursor.move_to_end_of_line();
 }
+
+pub fn move_word_forward(app: &mut Application, _: &char) {
+    let buffer = app.workspace.current_buffer().unwrap();
+    let data = buffer.data();
+    let lines = buffer_lines(&data);
+    let destination = word_forward(&lines, *buffer.cursor, false);
+    buffer.cursor.move_to(destination);
+}
+
+pub fn move_word_backward(app: &mut Application, _: &char) {
+    let buffer = app.workspace.current_buffer().unwrap();
+    let data = buffer.data();
+    let lines = buffer_lines(&data);
+    let destination = word_backward(&lines, *buffer.cursor, false);
+    buffer.cursor.move_to(destination);
+}
+
+pub fn move_to_word_end(app: &mut Application, _: &char) {
+    let buffer = app.workspace.current_buffer().unwrap();
+    let data = buffer.data();
+    let lines = buffer_lines(&data);
+    let destination = word_end(&lines, *buffer.cursor, false);
+    buffer.cursor.move_to(destination);
+}
+
+pub fn move_full_word_forward(app: &mut Application, _: &char) {
+    let buffer = app.workspace.current_buffer().unwrap();
+    let data = buffer.data();
+    let lines = buffer_lines(&data);
+    let destination = word_forward(&lines, *buffer.cursor, true);
+    buffer.cursor.move_to(destination);
+}
+
+pub fn move_full_word_backward(app: &mut Application, _: &char) {
+    let buffer = app.workspace.current_buffer().unwrap();
+    let data = buffer.data();
+    let lines = buffer_lines(&data);
+    let destination = word_backward(&lines, *buffer.cursor, true);
+    buffer.cursor.move_to(destination);
+}
+
+pub fn move_to_full_word_end(app: &mut Application, _: &char) {
+    let buffer = app.workspace.current_buffer().unwrap();
+    let data = buffer.data();
+    let lines = buffer_lines(&data);
+    let destination = word_end(&lines, *buffer.cursor, true);
+    buffer.cursor.move_to(destination);
+}
+
+pub fn move_to_next_paragraph(app: &mut Application, _: &char) {
+    let buffer = app.workspace.current_buffer().unwrap();
+    let data = buffer.data();
+    let lines = buffer_lines(&data);
+    let destination = next_paragraph(&lines, buffer.cursor.line);
+    buffer.cursor.move_to(destination);
+}
+
+pub fn move_to_previous_paragraph(app: &mut Application, _: &char) {
+    let buffer = app.workspace.current_buffer().unwrap();
+    let data = buffer.data();
+    let lines = buffer_lines(&data);
+    let destination = previous_paragraph(&lines, buffer.cursor.line);
+    buffer.cursor.move_to(destination);
+}
+
+pub fn move_to_matching_bracket(app: &mut Application, _: &char) {
+    let buffer = app.workspace.current_buffer().unwrap();
+    let data = buffer.data();
+    let lines = buffer_lines(&data);
+    let position = *buffer.cursor;
+
+    let current = match char_at(&lines, position) {
+        Some(c) => c,
+        None => return,
+    };
+
+    let destination = if let Some(index) = OPENING_BRACKETS.iter().position(|&c| c == current) {
+        matching_bracket_forward(&lines, position, OPENING_BRACKETS[index], CLOSING_BRACKETS[index])
+    } else if let Some(index) = CLOSING_BRACKETS.iter().position(|&c| c == current) {
+        matching_bracket_backward(&lines, position, OPENING_BRACKETS[index], CLOSING_BRACKETS[index])
+    } else {
+        None
+    };
+
+    if let Some(destination) = destination {
+        buffer.cursor.move_to(destination);
+    }
+}
+
+const OPENING_BRACKETS: [char; 3] = ['(', '[', '{'];
+const CLOSING_BRACKETS: [char; 3] = [')', ']', '}'];
+
+// A character's class determines where word boundaries fall; a boundary
+// occurs wherever the class changes, or when crossing from whitespace
+// into non-whitespace.
+#[derive(PartialEq, Clone, Copy)]
+enum CharClass {
+    Whitespace,
+    Word,
+    Punctuation,
+}
+
+fn classify(character: char, whitespace_delimited: bool) -> CharClass {
+    if character.is_whitespace() {
+        CharClass::Whitespace
+    } else if whitespace_delimited {
+        CharClass::Word
+    } else if character.is_alphanumeric() || character == '_' {
+        CharClass::Word
+    } else {
+        CharClass::Punctuation
+    }
+}
+
+fn buffer_lines(data: &str) -> Vec<&str> {
+    data.lines().collect()
+}
+
+fn char_at(lines: &[&str], position: Position) -> Option<char> {
+    lines.get(position.line).and_then(|line| line.chars().nth(position.offset))
+}
+
+fn line_length(lines: &[&str], line: usize) -> usize {
+    lines.get(line).map(|line| line.chars().count()).unwrap_or(0)
+}
+
+// The position immediately past the end of a line is treated as whitespace,
+// since it represents the newline that separates it from the next line.
+fn class_at(lines: &[&str], position: Position, whitespace_delimited: bool) -> CharClass {
+    match char_at(lines, position) {
+        Some(character) => classify(character, whitespace_delimited),
+        None => CharClass::Whitespace,
+    }
+}
+
+fn advance(lines: &[&str], position: Position) -> Option<Position> {
+    if position.offset < line_length(lines, position.line) {
+        Some(Position{ line: position.line, offset: position.offset + 1 })
+    } else if position.line + 1 < lines.len() {
+        Some(Position{ line: position.line + 1, offset: 0 })
+    } else {
+        None
+    }
+}
+
+fn retreat(lines: &[&str], position: Position) -> Option<Position> {
+    if position.offset > 0 {
+        Some(Position{ line: position.line, offset: position.offset - 1 })
+    } else if position.line > 0 {
+        let previous_line = position.line - 1;
+        Some(Position{ line: previous_line, offset: line_length(lines, previous_line) })
+    } else {
+        None
+    }
+}
+
+fn word_forward(lines: &[&str], start: Position, whitespace_delimited: bool) -> Position {
+    let mut position = start;
+    let starting_class = class_at(lines, position, whitespace_delimited);
+
+    // Skip the remainder of the current run of same-class characters.
+    while class_at(lines, position, whitespace_delimited) == starting_class {
+        match advance(lines, position) {
+            Some(next) => position = next,
+            None => return position,
+        }
+    }
+
+    // Skip any whitespace to land on the next non-blank character.
+    while class_at(lines, position, whitespace_delimited) == CharClass::Whitespace {
+        match advance(lines, position) {
+            Some(next) => position = next,
+            None => return position,
+        }
+    }
+
+    position
+}
+
+fn word_backward(lines: &[&str], start: Position, whitespace_delimited: bool) -> Position {
+    let mut position = match retreat(lines, start) {
+        Some(previous) => previous,
+        None => return start,
+    };
+
+    // Skip any whitespace separating us from the previous word.
+    while class_at(lines, position, whitespace_delimited) == CharClass::Whitespace {
+        match retreat(lines, position) {
+            Some(previous) => position = previous,
+            None => return position,
+        }
+    }
+
+    // Scan to the start of this same-class run.
+    let class = class_at(lines, position, whitespace_delimited);
+    loop {
+        match retreat(lines, position) {
+            Some(previous) if class_at(lines, previous, whitespace_delimited) == class => position = previous,
+            _ => break,
+        }
+    }
+
+    position
+}
+
+fn word_end(lines: &[&str], start: Position, whitespace_delimited: bool) -> Position {
+    let mut position = match advance(lines, start) {
+        Some(next) => next,
+        None => return start,
+    };
+
+    // Skip any whitespace separating us from the next word.
+    while class_at(lines, position, whitespace_delimited) == CharClass::Whitespace {
+        match advance(lines, position) {
+            Some(next) => position = next,
+            None => return position,
+        }
+    }
+
+    // Scan to the last character of this same-class run.
+    let class = class_at(lines, position, whitespace_delimited);
+    loop {
+        match advance(lines, position) {
+            Some(next) if class_at(lines, next, whitespace_delimited) == class => position = next,
+            _ => break,
+        }
+    }
+
+    position
+}
+
+fn next_paragraph(lines: &[&str], line: usize) -> Position {
+    let last_line = lines.len().saturating_sub(1);
+
+    let mut line = line + 1;
+    while line < last_line && !lines[line].is_empty() {
+        line += 1;
+    }
+
+    Position{ line: line.min(last_line), offset: 0 }
+}
+
+fn previous_paragraph(lines: &[&str], line: usize) -> Position {
+    let mut line = line;
+    while line > 0 {
+        line -= 1;
+        if lines[line].is_empty() {
+            break;
+        }
+    }
+
+    Position{ line: line, offset: 0 }
+}
+
+fn matching_bracket_forward(lines: &[&str], start: Position, opening: char, closing: char) -> Option<Position> {
+    let mut depth = 0;
+    let mut position = start;
+
+    loop {
+        match char_at(lines, position) {
+            Some(character) if character == opening => depth += 1,
+            Some(character) if character == closing => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(position);
+                }
+            }
+            _ => (),
+        }
+
+        position = advance(lines, position)?;
+    }
+}
+
+fn matching_bracket_backward(lines: &[&str], start: Position, opening: char, closing: char) -> Option<Position> {
+    let mut depth = 0;
+    let mut position = start;
+
+    loop {
+        match char_at(lines, position) {
+            Some(character) if character == closing => depth += 1,
+            Some(character) if character == opening => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(position);
+                }
+            }
+            _ => (),
+        }
+
+        position = retreat(lines, position)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn word_forward_skips_current_word_and_lands_on_next() {
+        let data = "amp editor";
+        let lines = buffer_lines(data);
+
+        let destination = word_forward(&lines, Position{ line: 0, offset: 0 }, false);
+
+        assert_eq!(destination, Position{ line: 0, offset: 4 });
+    }
+
+    #[test]
+    fn word_forward_wraps_to_the_next_lines_first_non_blank() {
+        let data = "amp\n  editor";
+        let lines = buffer_lines(data);
+
+        let destination = word_forward(&lines, Position{ line: 0, offset: 0 }, false);
+
+        assert_eq!(destination, Position{ line: 1, offset: 2 });
+    }
+
+    #[test]
+    fn word_forward_treats_punctuation_as_its_own_class() {
+        let data = "amp.editor";
+        let lines = buffer_lines(data);
+
+        let destination = word_forward(&lines, Position{ line: 0, offset: 0 }, false);
+
+        assert_eq!(destination, Position{ line: 0, offset: 3 });
+    }
+
+    #[test]
+    fn full_word_forward_treats_punctuation_as_part_of_the_word() {
+        let data = "amp.editor jumps";
+        let lines = buffer_lines(data);
+
+        let destination = word_forward(&lines, Position{ line: 0, offset: 0 }, true);
+
+        assert_eq!(destination, Position{ line: 0, offset: 11 });
+    }
+
+    #[test]
+    fn word_backward_is_the_mirror_image_of_word_forward() {
+        let data = "amp editor";
+        let lines = buffer_lines(data);
+
+        let destination = word_backward(&lines, Position{ line: 0, offset: 4 }, false);
+
+        assert_eq!(destination, Position{ line: 0, offset: 0 });
+    }
+
+    #[test]
+    fn word_end_advances_at_least_one_character_before_scanning() {
+        let data = "amp editor";
+        let lines = buffer_lines(data);
+
+        let destination = word_end(&lines, Position{ line: 0, offset: 0 }, false);
+
+        assert_eq!(destination, Position{ line: 0, offset: 2 });
+
+        let next_destination = word_end(&lines, destination, false);
+
+        assert_eq!(next_destination, Position{ line: 0, offset: 9 });
+    }
+
+    #[test]
+    fn next_paragraph_stops_on_the_next_blank_line() {
+        let data = "amp\neditor\n\njumps";
+        let lines = buffer_lines(data);
+
+        let destination = next_paragraph(&lines, 0);
+
+        assert_eq!(destination, Position{ line: 2, offset: 0 });
+    }
+
+    #[test]
+    fn next_paragraph_from_a_blank_line_skips_to_the_next_one() {
+        let data = "amp\n\neditor\n\njumps";
+        let lines = buffer_lines(data);
+
+        let destination = next_paragraph(&lines, 1);
+
+        assert_eq!(destination, Position{ line: 3, offset: 0 });
+    }
+
+    #[test]
+    fn next_paragraph_stops_at_the_last_line_when_there_is_no_blank_line() {
+        let data = "amp\neditor\njumps";
+        let lines = buffer_lines(data);
+
+        let destination = next_paragraph(&lines, 0);
+
+        assert_eq!(destination, Position{ line: 2, offset: 0 });
+    }
+
+    #[test]
+    fn previous_paragraph_stops_on_the_previous_blank_line() {
+        let data = "amp\n\neditor\njumps";
+        let lines = buffer_lines(data);
+
+        let destination = previous_paragraph(&lines, 3);
+
+        assert_eq!(destination, Position{ line: 1, offset: 0 });
+    }
+
+    #[test]
+    fn previous_paragraph_from_the_first_line_stays_put() {
+        let data = "amp\neditor";
+        let lines = buffer_lines(data);
+
+        let destination = previous_paragraph(&lines, 0);
+
+        assert_eq!(destination, Position{ line: 0, offset: 0 });
+    }
+
+    #[test]
+    fn previous_paragraph_stops_at_the_first_line_when_there_is_no_blank_line() {
+        let data = "amp\neditor\njumps";
+        let lines = buffer_lines(data);
+
+        let destination = previous_paragraph(&lines, 2);
+
+        assert_eq!(destination, Position{ line: 0, offset: 0 });
+    }
+
+    #[test]
+    fn matching_bracket_forward_tracks_nesting_depth() {
+        let data = "(foo (bar) baz)";
+        let lines = buffer_lines(data);
+
+        let destination = matching_bracket_forward(&lines, Position{ line: 0, offset: 0 }, '(', ')');
+
+        assert_eq!(destination, Some(Position{ line: 0, offset: 14 }));
+    }
+
+    #[test]
+    fn matching_bracket_backward_tracks_nesting_depth() {
+        let data = "(foo (bar) baz)";
+        let lines = buffer_lines(data);
+
+        let destination = matching_bracket_backward(&lines, Position{ line: 0, offset: 14 }, '(', ')');
+
+        assert_eq!(destination, Some(Position{ line: 0, offset: 0 }));
+    }
+
+    #[test]
+    fn matching_bracket_forward_returns_none_when_unmatched() {
+        let data = "(foo bar";
+        let lines = buffer_lines(data);
+
+        let destination = matching_bracket_forward(&lines, Position{ line: 0, offset: 0 }, '(', ')');
+
+        assert_eq!(destination, None);
+    }
+}
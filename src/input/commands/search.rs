@@ -0,0 +1,20 @@
+use application::Application;
+
+pub fn search_next(app: &mut Application, _: &char) {
+    let position = *app.workspace.current_buffer().unwrap().cursor;
+    app.search.select_next(position);
+    move_cursor_to_current_match(app);
+}
+
+pub fn search_previous(app: &mut Application, _: &char) {
+    let position = *app.workspace.current_buffer().unwrap().cursor;
+    app.search.select_previous(position);
+    move_cursor_to_current_match(app);
+}
+
+fn move_cursor_to_current_match(app: &mut Application) {
+    if let Some(range) = app.search.current_match() {
+        let destination = range.start();
+        app.workspace.current_buffer().unwrap().cursor.move_to(destination);
+    }
+}
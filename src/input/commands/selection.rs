@@ -0,0 +1,32 @@
+use application::Application;
+use view::selection::{Selection, SelectionMode};
+
+pub fn start_selection(app: &mut Application, _: &char) {
+    start_selection_with_mode(app, SelectionMode::Character);
+}
+
+pub fn start_line_selection(app: &mut Application, _: &char) {
+    start_selection_with_mode(app, SelectionMode::Line);
+}
+
+pub fn start_block_selection(app: &mut Application, _: &char) {
+    start_selection_with_mode(app, SelectionMode::Block);
+}
+
+// Extending leaves an in-progress selection's anchor untouched; its
+// resolved span already grows and shrinks as the cursor moves. This only
+// needs to act when there's no selection yet to extend.
+pub fn extend_selection(app: &mut Application, _: &char) {
+    if app.selection.is_none() {
+        start_selection_with_mode(app, SelectionMode::Character);
+    }
+}
+
+pub fn clear_selection(app: &mut Application, _: &char) {
+    app.selection = None;
+}
+
+fn start_selection_with_mode(app: &mut Application, mode: SelectionMode) {
+    let anchor = *app.workspace.current_buffer().unwrap().cursor;
+    app.selection = Some(Selection::new(anchor, mode));
+}
@@ -2,43 +2,152 @@ use rustbox;
 use rustbox::{Color, Event, Style};
 use scribe::buffer::{Buffer, Lexeme, Position, Range, Token};
 use view::color;
+use view::color::Theme;
+use view::screen_buffer::{Cell, ScreenBuffer};
+use view::selection::Selection;
 use view::terminal::Terminal;
 
-const LINE_LENGTH_GUIDE_OFFSET: usize = 80;
-const LINE_WRAPPING: bool = true;
-const TAB_WIDTH: usize = 4;
+pub const DEFAULT_LINE_LENGTH_GUIDE_OFFSET: usize = 80;
+pub const DEFAULT_LINE_WRAPPING: bool = true;
+pub const DEFAULT_TAB_WIDTH: usize = 4;
+pub const DEFAULT_WRAP_MARKER: char = '\u{21aa}';
+pub const DEFAULT_OVERFLOW_MARKER: char = '\u{2026}';
+
+/// The subset of editor settings that affect soft-wrap and tab
+/// presentation, broken out of the constructor's already-long parameter
+/// list since they're typically read from configuration as a group.
+pub struct RenderSettings {
+    pub wrap_lines: bool,
+    pub tab_width: usize,
+    pub length_guide_offset: usize,
+    pub wrap_marker: char,
+    pub overflow_marker: char,
+}
+
+impl Default for RenderSettings {
+    fn default() -> RenderSettings {
+        RenderSettings{
+            wrap_lines: DEFAULT_LINE_WRAPPING,
+            tab_width: DEFAULT_TAB_WIDTH,
+            length_guide_offset: DEFAULT_LINE_LENGTH_GUIDE_OFFSET,
+            wrap_marker: DEFAULT_WRAP_MARKER,
+            overflow_marker: DEFAULT_OVERFLOW_MARKER,
+        }
+    }
+}
+
+/// How line numbers in the gutter are displayed: each line's absolute
+/// position in the buffer, its distance from the cursor line, or the
+/// cursor line's absolute number with every other line shown relative
+/// to it (vi's "hybrid" mode).
+#[derive(Clone, Copy, PartialEq)]
+pub enum LineNumberMode {
+    Absolute,
+    Relative,
+    Hybrid,
+}
 
 /// A one-time-use type that encapsulates all of the
 /// idiosyncracies involved in rendering a buffer to the screen.
 pub struct BufferRenderer<'a> {
     alt_background_color: Color,
+    back_buffer: &'a mut ScreenBuffer,
+    block_selection_columns: Option<(usize, usize)>,
     buffer: &'a Buffer,
     buffer_position: Position,
     cursor_visible: bool,
+    current_highlight: Option<usize>,
     gutter_width: usize,
-    highlight: Option<&'a Range>,
+    highlight_index: usize,
+    highlights: &'a [Range],
+    indent_line: Option<usize>,
+    configured_length_guide_offset: usize,
+    line_indent: usize,
+    line_number_mode: LineNumberMode,
     line_number_width: usize,
+    overflow_marker: char,
     screen_position: Position,
     scroll_offset: usize,
+    selection: Option<&'a Selection>,
+    tab_width: usize,
     terminal: &'a Terminal,
+    theme: &'a Theme,
+    truecolor: bool,
+    truncated: bool,
+    wrap_lines: bool,
+    wrap_marker: char,
 }
 
 impl<'a> BufferRenderer<'a> {
-    pub fn new(buffer: &'a Buffer, scroll_offset: usize, terminal: &'a Terminal, alt_background_color: Color, highlight: Option<&'a Range>) -> BufferRenderer<'a> {
-        // Determine the gutter size based on the number of lines.
-        let line_number_width = buffer.line_count().to_string().len() + 1;
+    /// Renders into `back_buffer` rather than printing straight to the
+    /// terminal; pass it to `view::screen_buffer::flush` afterwards to
+    /// paint only the cells that changed since the last frame.
+    ///
+    /// `highlights` must be sorted by start position; `current_highlight`,
+    /// when present, is the index into `highlights` of the match that
+    /// should be emphasized (e.g. the active search result) rather than
+    /// merely marked. `selection`, when present, takes priority over
+    /// highlights wherever the two overlap. `truecolor` controls whether
+    /// theme colors are rendered as 24-bit RGB or quantized to the
+    /// eight basic palette colors.
+    pub fn new(buffer: &'a Buffer, scroll_offset: usize, terminal: &'a Terminal, back_buffer: &'a mut ScreenBuffer, alt_background_color: Color, highlights: &'a [Range], current_highlight: Option<usize>, selection: Option<&'a Selection>, theme: &'a Theme, truecolor: bool, line_number_mode: LineNumberMode, settings: RenderSettings) -> BufferRenderer<'a> {
+        // In absolute mode, the gutter only ever needs to fit the
+        // highest line number in the buffer. In relative mode, every
+        // displayed number is a distance from the cursor line, so the
+        // widest one that can appear is bounded by the viewport height
+        // instead. Hybrid mode is a mix of the two: every line other
+        // than the cursor's shows a viewport-bounded distance, but the
+        // cursor line itself shows its absolute number, which can be
+        // wider on a buffer taller than the viewport — so its gutter has
+        // to fit whichever of the two is wider.
+        let line_number_width = match line_number_mode {
+            LineNumberMode::Absolute => buffer.line_count().to_string().len() + 1,
+            LineNumberMode::Relative => terminal.height().to_string().len() + 1,
+            LineNumberMode::Hybrid => terminal.height().to_string().len().max(buffer.line_count().to_string().len()) + 1,
+        };
+        let gutter_width = line_number_width + 2;
+
+        // A Block-mode selection's screen-column bounds depend on
+        // tab-expanding its anchor and cursor lines, which is too
+        // expensive to redo for every rendered glyph; the cursor doesn't
+        // move during a render pass, so it's resolved once up front and
+        // reused by `in_selection` for the whole frame.
+        let block_selection_columns = selection.and_then(|selection| selection.block_columns(buffer, *buffer.cursor, gutter_width, settings.tab_width));
 
         BufferRenderer{
             alt_background_color: alt_background_color,
+            back_buffer: back_buffer,
+            block_selection_columns: block_selection_columns,
             buffer: buffer,
             cursor_visible: false,
-            gutter_width: line_number_width + 2,
-            highlight: highlight,
+            current_highlight: current_highlight,
+            gutter_width: gutter_width,
+            highlight_index: 0,
+            highlights: highlights,
+            indent_line: None,
+            configured_length_guide_offset: settings.length_guide_offset,
+            line_indent: 0,
+            line_number_mode: line_number_mode,
             line_number_width: line_number_width,
+            overflow_marker: settings.overflow_marker,
             buffer_position: Position{ line: 0, offset: 0 },
             screen_position: Position{ line: 0, offset: 0 },
             scroll_offset: scroll_offset,
+            selection: selection,
+            tab_width: settings.tab_width,
             terminal: terminal,
+            theme: theme,
+            truecolor: truecolor,
+            truncated: false,
+            wrap_lines: settings.wrap_lines,
+            wrap_marker: settings.wrap_marker,
+        }
+    }
+
+    fn in_selection(&self) -> bool {
+        match self.selection {
+            Some(selection) => selection.contains(*self.buffer.cursor, self.buffer_position, self.screen_position, self.block_selection_columns),
+            None => false,
         }
     }
 
@@ -46,6 +155,14 @@ impl<'a> BufferRenderer<'a> {
         match token {
             &Token::Newline => self.advance_to_next_line(),
             &Token::Lexeme(ref lexeme) => {
+                // Compute the source line's leading-whitespace column once,
+                // the first time we see a lexeme on it, so wrapped
+                // continuations of this line can hang-indent to match.
+                if self.indent_line != Some(lexeme.position.line) {
+                    self.line_indent = self.compute_line_indent(lexeme.position.line);
+                    self.indent_line = Some(lexeme.position.line);
+                }
+
                 self.buffer_position = lexeme.position;
                 self.screen_position = lexeme.position;
                 self.screen_position.offset += self.gutter_width;
@@ -53,6 +170,17 @@ impl<'a> BufferRenderer<'a> {
         }
     }
 
+    fn compute_line_indent(&self, line: usize) -> usize {
+        let data = self.buffer.data();
+        let text = data.lines().nth(line).unwrap_or("");
+
+        // Tab-expand the leading whitespace the same way print_lexeme
+        // does, so the indent is a screen column rather than a count.
+        text.chars().take(leading_whitespace_width(text)).fold(0, |column, character| {
+            if character == '\t' { next_tab_stop(column, self.tab_width) } else { column + 1 }
+        })
+    }
+
     fn on_cursor_line(&self) -> bool {
         self.buffer_position.line == self.buffer.cursor.line
     }
@@ -60,41 +188,36 @@ impl<'a> BufferRenderer<'a> {
     fn print_line_highlight(&mut self) {
         if self.on_cursor_line() {
             for offset in self.screen_position.offset..self.terminal.width() {
-                self.terminal.print_char(offset,
+                self.back_buffer.set(offset,
                                 self.screen_position.line,
-                                rustbox::RB_NORMAL,
-                                Color::Default,
-                                self.alt_background_color,
-                                ' ');
+                                Cell{ character: ' ', style: rustbox::RB_NORMAL, foreground: Color::Default, background: self.alt_background_color });
             }
         }
     }
 
     fn print_length_guide(&mut self) {
         if !self.on_cursor_line() && self.screen_position.offset <= self.length_guide_offset() {
-            self.terminal.print_char(self.length_guide_offset(),
+            self.back_buffer.set(self.length_guide_offset(),
                             self.screen_position.line,
-                            rustbox::RB_NORMAL,
-                            Color::Default,
-                            self.alt_background_color,
-                            ' ');
+                            Cell{ character: ' ', style: rustbox::RB_NORMAL, foreground: Color::Default, background: self.alt_background_color });
         }
     }
 
     fn length_guide_offset(&self) -> usize {
-        self.gutter_width + LINE_LENGTH_GUIDE_OFFSET
+        self.gutter_width + self.configured_length_guide_offset
     }
 
     fn advance_to_next_line(&mut self) {
         self.print_line_highlight();
         self.print_length_guide();
 
+        self.truncated = false;
         self.buffer_position.line += 1;
         self.buffer_position.offset = 0;
         self.screen_position.line += 1;
 
         // Draw leading line number for the new line.
-        self.screen_position.offset = self.draw_line_number(self.screen_position.line, self.buffer_position.line + 1, self.buffer_position.line == self.buffer.cursor.line, self.line_number_width);
+        self.screen_position.offset = self.draw_line_number(self.screen_position.line, self.buffer_position.line, self.buffer_position.line == self.buffer.cursor.line, self.line_number_width);
     }
 
     // Check if we've arrived at the buffer's cursor position,
@@ -107,32 +230,61 @@ impl<'a> BufferRenderer<'a> {
         }
     }
 
-    fn current_char_style(&self, token_color: Color) -> (Style, Color) {
-        match self.highlight {
-            Some(ref highlight_range) => {
-                if highlight_range.includes(&self.buffer_position) {
-                    (rustbox::RB_REVERSE, Color::Default)
+    // Highlights are sorted by start position, so as rendering proceeds
+    // down the buffer we only need to advance past the ranges that have
+    // already ended, rather than re-scanning the whole collection for
+    // every glyph.
+    fn advance_highlight_index(&mut self) {
+        while self.highlight_index < self.highlights.len() &&
+              self.highlights[self.highlight_index].end() <= self.buffer_position {
+            self.highlight_index += 1;
+        }
+    }
+
+    fn current_char_style(&mut self, scope_style: color::ScopeStyle) -> (Style, Color, Color) {
+        // Selection membership is checked before highlights, since a
+        // selected match should still read as selected.
+        if self.in_selection() {
+            return (rustbox::RB_NORMAL, Color::White, Color::Blue);
+        }
+
+        self.advance_highlight_index();
+
+        for (offset, highlight) in self.highlights[self.highlight_index..].iter().enumerate() {
+            if highlight.start() > self.buffer_position {
+                break;
+            }
+
+            if highlight.includes(&self.buffer_position) {
+                return if Some(self.highlight_index + offset) == self.current_highlight {
+                    (rustbox::RB_REVERSE, Color::Default, self.background_color(None))
                 } else {
-                    (rustbox::RB_NORMAL, token_color)
-                }
+                    (rustbox::RB_NORMAL, Color::Default, Color::Yellow)
+                };
             }
-            None => (rustbox::RB_NORMAL, token_color)
         }
+
+        let foreground = color::to_rustbox_color(scope_style.foreground, self.truecolor);
+        let background = scope_style.background.map(|background| color::to_rustbox_color(background, self.truecolor));
+
+        (scope_style.font_style.to_style(), foreground, self.background_color(background))
     }
 
-    fn background_color(&self) -> Color {
-        if self.on_cursor_line() {
+    fn background_color(&self, scope_background: Option<Color>) -> Color {
+        if self.in_selection() {
+            Color::Blue
+        } else if self.on_cursor_line() {
             self.alt_background_color
         } else {
-            Color::Default
+            scope_background.unwrap_or(Color::Default)
         }
     }
 
     pub fn print_lexeme(&mut self, lexeme: Lexeme) {
-        let token_color = if let Some(ref scope) = lexeme.scope {
-            color::map(scope)
+        let scope_style = if let Some(ref scope) = lexeme.scope {
+            color::map(self.theme, scope)
         } else {
-            Color::Default
+            self.theme.default_style
         };
 
         for character in lexeme.value.chars() {
@@ -142,29 +294,42 @@ impl<'a> BufferRenderer<'a> {
 
             self.set_cursor();
 
-            let (style, color) = self.current_char_style(token_color);
+            // Once a non-wrapping line has overflowed the viewport, the
+            // rest of its characters are off-screen; just keep tracking
+            // buffer position for cursor/highlight accuracy.
+            if !self.wrap_lines && self.truncated {
+                self.buffer_position.offset += 1;
+                continue;
+            }
+
+            let (style, color, background_color) = self.current_char_style(scope_style);
 
-            if LINE_WRAPPING && self.screen_position.offset == self.terminal.width() {
+            if self.wrap_lines && self.screen_position.offset == self.terminal.width() {
                 self.screen_position.line += 1;
-                self.screen_position.offset = self.gutter_width;
-                self.terminal.print_char(self.screen_position.offset, self.screen_position.line, style, color, self.background_color(), character);
+                self.screen_position.offset = self.gutter_width + self.line_indent;
+                self.print_wrap_marker();
+                self.back_buffer.set(self.screen_position.offset, self.screen_position.line, Cell{ character: character, style: style, foreground: color, background: background_color });
                 self.screen_position.offset += 1;
                 self.buffer_position.offset += 1;
+            } else if !self.wrap_lines && self.screen_position.offset == self.terminal.width() {
+                self.back_buffer.set(self.screen_position.offset - 1, self.screen_position.line, Cell{ character: self.overflow_marker, style: rustbox::RB_NORMAL, foreground: Color::Default, background: background_color });
+                self.truncated = true;
+                self.buffer_position.offset += 1;
             } else if character == '\t' {
                 // Calculate the next tab stop using the tab-aware offset,
                 // *without considering the line number gutter*, and then
                 // re-add the gutter width to get the actual/screen offset.
-                let buffer_tab_stop = next_tab_stop(self.screen_position.offset - self.gutter_width);
+                let buffer_tab_stop = next_tab_stop(self.screen_position.offset - self.gutter_width, self.tab_width);
                 let screen_tab_stop = buffer_tab_stop + self.gutter_width;
 
                 // Print the sequence of spaces and move the offset accordingly.
                 for _ in self.screen_position.offset..screen_tab_stop {
-                    self.terminal.print_char(self.screen_position.offset, self.screen_position.line, style, color, self.alt_background_color, ' ');
+                    self.back_buffer.set(self.screen_position.offset, self.screen_position.line, Cell{ character: ' ', style: style, foreground: color, background: self.alt_background_color });
                     self.screen_position.offset += 1;
                 }
                 self.buffer_position.offset += 1;
             } else {
-                self.terminal.print_char(self.screen_position.offset, self.screen_position.line, style, color, self.background_color(), character);
+                self.back_buffer.set(self.screen_position.offset, self.screen_position.line, Cell{ character: character, style: style, foreground: color, background: background_color });
                 self.screen_position.offset += 1;
                 self.buffer_position.offset += 1;
             }
@@ -173,6 +338,18 @@ impl<'a> BufferRenderer<'a> {
         }
     }
 
+    // Prints the wrap-continuation marker in the gutter of a soft-wrapped
+    // row, in place of the (suppressed) line number, so a reader can tell
+    // a visual line break apart from a real one.
+    fn print_wrap_marker(&mut self) {
+        if self.gutter_width < 2 {
+            return;
+        }
+
+        let marker_column = self.gutter_width - 2;
+        self.back_buffer.set(marker_column, self.screen_position.line, Cell{ character: self.wrap_marker, style: rustbox::RB_NORMAL, foreground: Color::Default, background: self.alt_background_color });
+    }
+
     fn before_visible_content(&self) -> bool {
         self.buffer_position.line < self.scroll_offset
     }
@@ -184,7 +361,7 @@ impl<'a> BufferRenderer<'a> {
     pub fn render(&mut self) {
         // Draw the first line number.
         // Others will be drawn following newline characters.
-        self.screen_position.offset = self.draw_line_number(0, self.scroll_offset + 1, self.buffer.cursor.line == self.scroll_offset, self.line_number_width);
+        self.screen_position.offset = self.draw_line_number(0, self.scroll_offset, self.buffer.cursor.line == self.scroll_offset, self.line_number_width);
 
         if let Some(tokens) = self.buffer.tokens() {
             'print: for token in tokens.iter() {
@@ -221,11 +398,21 @@ impl<'a> BufferRenderer<'a> {
         self.print_length_guide();
     }
 
-    fn draw_line_number(&self, line: usize, line_number: usize, cursor_line: bool, width: usize) -> usize {
+    fn draw_line_number(&mut self, line: usize, buffer_line: usize, cursor_line: bool, width: usize) -> usize {
         let mut offset = 0;
 
+        let displayed_number = match self.line_number_mode {
+            LineNumberMode::Absolute => buffer_line + 1,
+            LineNumberMode::Relative => {
+                if cursor_line { 0 } else { line_distance(buffer_line, self.buffer.cursor.line) }
+            }
+            LineNumberMode::Hybrid => {
+                if cursor_line { buffer_line + 1 } else { line_distance(buffer_line, self.buffer.cursor.line) }
+            }
+        };
+
         // Get left-padded string-based line number.
-        let formatted_line_number = format!("{:>width$}  ", line_number, width = width);
+        let formatted_line_number = format!("{:>width$}  ", displayed_number, width = width);
 
         // Print numbers.
         for number in formatted_line_number.chars() {
@@ -244,12 +431,7 @@ impl<'a> BufferRenderer<'a> {
                 rustbox::RB_NORMAL
             };
 
-            self.terminal.print_char(offset,
-                            line,
-                            weight,
-                            Color::Default,
-                            background_color,
-                            number);
+            self.back_buffer.set(offset, line, Cell{ character: number, style: weight, foreground: Color::Default, background: background_color });
 
             offset += 1;
         }
@@ -257,6 +439,49 @@ impl<'a> BufferRenderer<'a> {
     }
 }
 
-fn next_tab_stop(offset: usize) -> usize {
-    (offset / TAB_WIDTH + 1) * TAB_WIDTH
-}
\ No newline at end of file
+pub(crate) fn next_tab_stop(offset: usize, tab_width: usize) -> usize {
+    (offset / tab_width + 1) * tab_width
+}
+
+fn line_distance(line: usize, cursor_line: usize) -> usize {
+    if line > cursor_line {
+        line - cursor_line
+    } else {
+        cursor_line - line
+    }
+}
+
+// The number of leading whitespace characters (spaces and tabs) on a
+// line: the prefix that `compute_line_indent` tab-expands into the column
+// a wrapped line's continuation rows hang-indent to. Broken out as a pure
+// function so it's testable without a full `BufferRenderer`.
+fn leading_whitespace_width(text: &str) -> usize {
+    text.chars().take_while(|character| *character == ' ' || *character == '\t').count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_tab_stop_advances_to_the_next_multiple_of_tab_width() {
+        assert_eq!(next_tab_stop(0, 4), 4);
+        assert_eq!(next_tab_stop(1, 4), 4);
+        assert_eq!(next_tab_stop(4, 4), 8);
+        assert_eq!(next_tab_stop(5, 4), 8);
+    }
+
+    #[test]
+    fn line_distance_is_symmetric() {
+        assert_eq!(line_distance(10, 4), 6);
+        assert_eq!(line_distance(4, 10), 6);
+        assert_eq!(line_distance(7, 7), 0);
+    }
+
+    #[test]
+    fn leading_whitespace_width_counts_spaces_and_tabs() {
+        assert_eq!(leading_whitespace_width("  \tfn main() {"), 3);
+        assert_eq!(leading_whitespace_width("no leading whitespace"), 0);
+        assert_eq!(leading_whitespace_width("    "), 4);
+    }
+}
@@ -1,4 +1,5 @@
 pub use termion::color::Rgb as RGBColor;
+use rustbox::{Color, Style, RB_BOLD, RB_NORMAL, RB_UNDERLINE};
 
 /// A convenience type used to represent a foreground/background
 /// color combination. Provides generic/convenience variants to
@@ -16,3 +17,462 @@ pub enum Colors {
     CustomFocusedForeground(RGBColor),
     Custom(RGBColor, RGBColor),
 }
+
+/// Bold/italic/underline toggles, parsed from a TextMate-style rule's
+/// `fontStyle` string (e.g. "bold italic").
+#[derive(Clone, Copy, Default)]
+pub struct FontStyle {
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+impl FontStyle {
+    pub fn parse(value: &str) -> FontStyle {
+        FontStyle{
+            bold: value.contains("bold"),
+            italic: value.contains("italic"),
+            underline: value.contains("underline"),
+        }
+    }
+
+    // Folds these toggles into a rustbox::Style bitmask. Italic has no
+    // rustbox equivalent, since most terminals can't render it reliably;
+    // it's still tracked here so a future backend that supports it can
+    // use it without another pass through the theme.
+    pub fn to_style(&self) -> Style {
+        let mut style = RB_NORMAL;
+        if self.bold { style = style | RB_BOLD; }
+        if self.underline { style = style | RB_UNDERLINE; }
+        style
+    }
+}
+
+/// A fully-resolved per-scope style: foreground, an optional background
+/// (scopes without one fall through to the renderer's own background),
+/// and font-weight attributes.
+#[derive(Clone, Copy)]
+pub struct ScopeStyle {
+    pub foreground: RGBColor,
+    pub background: Option<RGBColor>,
+    pub font_style: FontStyle,
+}
+
+/// One TextMate-style rule: a dotted scope selector (e.g.
+/// "string.quoted.double") and the style it applies.
+pub struct ThemeRule {
+    pub scope: String,
+    pub style: ScopeStyle,
+}
+
+/// An ordered set of scope rules, plus the style used when nothing more
+/// specific matches.
+pub struct Theme {
+    pub rules: Vec<ThemeRule>,
+    pub default_style: ScopeStyle,
+}
+
+impl Theme {
+    pub fn new(rules: Vec<ThemeRule>, default_style: ScopeStyle) -> Theme {
+        Theme{ rules: rules, default_style: default_style }
+    }
+
+    /// Resolves a lexeme's scope path to the style of its most specific
+    /// matching rule: the one whose selector is the longest dotted-prefix
+    /// match (e.g. a rule for "string" matches "string.quoted.double",
+    /// but a rule for "string.quoted" matches more specifically and
+    /// wins). Falls back to the theme's default style.
+    pub fn resolve(&self, scope: &str) -> ScopeStyle {
+        self.rules.iter()
+            .filter(|rule| scope_matches(scope, &rule.scope))
+            .max_by_key(|rule| rule.scope.len())
+            .map(|rule| rule.style)
+            .unwrap_or(self.default_style)
+    }
+}
+
+fn scope_matches(scope: &str, selector: &str) -> bool {
+    scope == selector || scope.starts_with(&format!("{}.", selector))
+}
+
+// The eight basic terminal colors a truecolor RGB value is quantized down
+// to for terminals that don't advertise truecolor support. The bright
+// half of the 16-color palette is not used.
+const PALETTE: [(Color, (u8, u8, u8)); 8] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::White, (229, 229, 229)),
+];
+
+fn quantize(color: RGBColor) -> Color {
+    let RGBColor(r, g, b) = color;
+
+    PALETTE.iter()
+        .min_by_key(|&&(_, (palette_r, palette_g, palette_b))| {
+            let delta_r = r as i32 - palette_r as i32;
+            let delta_g = g as i32 - palette_g as i32;
+            let delta_b = b as i32 - palette_b as i32;
+            delta_r * delta_r + delta_g * delta_g + delta_b * delta_b
+        })
+        .map(|&(color, _)| color)
+        .unwrap_or(Color::Default)
+}
+
+/// Converts a theme's RGB value to a renderable `rustbox::Color`,
+/// quantizing to the nearest of the eight basic palette colors unless the
+/// terminal has advertised truecolor support.
+pub fn to_rustbox_color(color: RGBColor, truecolor: bool) -> Color {
+    if truecolor {
+        Color::Rgb(color.0, color.1, color.2)
+    } else {
+        quantize(color)
+    }
+}
+
+/// Resolves a lexeme's scope against `theme`, returning the style it
+/// should be rendered with.
+pub fn map(theme: &Theme, scope: &str) -> ScopeStyle {
+    theme.resolve(scope)
+}
+
+/// Loads a TextMate-style theme from its JSON source: a top-level object
+/// with a `default` settings block and an ordered `rules` array, each
+/// entry an object holding a dotted `scope` selector alongside its own
+/// settings keys (they are not nested under a `settings` key, as in a
+/// VS Code-style `tokenColors` entry). Settings carry a required
+/// `foreground` and an optional `background` (6-digit hex strings, e.g.
+/// `"#d4d4d4"`), and an optional `fontStyle` (a space-separated string
+/// of `"bold"`/`"italic"`/`"underline"`).
+pub fn load(source: &str) -> Result<Theme, String> {
+    let root = json::parse(source)?;
+    if root.as_object().is_none() {
+        return Err("a theme must be a JSON object".to_string());
+    }
+
+    let default_style = root.get("default")
+        .ok_or_else(|| "theme is missing its \"default\" settings block".to_string())
+        .and_then(parse_scope_style)?;
+
+    let rules = match root.get("rules") {
+        Some(rules) => {
+            let rules = rules.as_array().ok_or("theme \"rules\" must be an array")?;
+            rules.iter().map(parse_rule).collect::<Result<Vec<ThemeRule>, String>>()?
+        }
+        None => Vec::new(),
+    };
+
+    Ok(Theme::new(rules, default_style))
+}
+
+fn parse_rule(value: &json::Value) -> Result<ThemeRule, String> {
+    if value.as_object().is_none() {
+        return Err("each theme rule must be an object".to_string());
+    }
+
+    let scope = value.get("scope")
+        .and_then(json::Value::as_str)
+        .ok_or("theme rule is missing its \"scope\" string")?
+        .to_string();
+
+    Ok(ThemeRule{ scope: scope, style: parse_scope_style(value)? })
+}
+
+fn parse_scope_style(value: &json::Value) -> Result<ScopeStyle, String> {
+    if value.as_object().is_none() {
+        return Err("theme rule settings must be an object".to_string());
+    }
+
+    let foreground = value.get("foreground")
+        .and_then(json::Value::as_str)
+        .ok_or_else(|| "theme rule is missing its \"foreground\" color".to_string())
+        .and_then(parse_hex_color)?;
+
+    let background = match value.get("background").and_then(json::Value::as_str) {
+        Some(hex) => Some(parse_hex_color(hex)?),
+        None => None,
+    };
+
+    let font_style = value.get("fontStyle")
+        .and_then(json::Value::as_str)
+        .map(FontStyle::parse)
+        .unwrap_or_default();
+
+    Ok(ScopeStyle{ foreground: foreground, background: background, font_style: font_style })
+}
+
+fn parse_hex_color(hex: &str) -> Result<RGBColor, String> {
+    let hex = hex.trim_start_matches('#');
+    if hex.chars().count() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("\"{}\" is not a 6-digit hex color", hex));
+    }
+
+    let r = u8::from_str_radix(&hex[0..2], 16).map_err(|e| e.to_string())?;
+    let g = u8::from_str_radix(&hex[2..4], 16).map_err(|e| e.to_string())?;
+    let b = u8::from_str_radix(&hex[4..6], 16).map_err(|e| e.to_string())?;
+
+    Ok(RGBColor(r, g, b))
+}
+
+// A JSON reader covering exactly the subset theme files need — objects,
+// arrays, and strings — since no JSON crate is available to this crate
+// yet. Numbers/booleans/null aren't part of the theme schema (colors and
+// font styles are always strings), so they're deliberately left out
+// rather than built and never exercised.
+mod json {
+    use std::iter::Peekable;
+    use std::str::Chars;
+
+    #[derive(Debug)]
+    pub enum Value {
+        Object(Vec<(String, Value)>),
+        Array(Vec<Value>),
+        String(String),
+    }
+
+    impl Value {
+        pub fn as_object(&self) -> Option<&[(String, Value)]> {
+            match *self {
+                Value::Object(ref entries) => Some(entries),
+                _ => None,
+            }
+        }
+
+        pub fn as_array(&self) -> Option<&[Value]> {
+            match *self {
+                Value::Array(ref entries) => Some(entries),
+                _ => None,
+            }
+        }
+
+        pub fn as_str(&self) -> Option<&str> {
+            match *self {
+                Value::String(ref value) => Some(value),
+                _ => None,
+            }
+        }
+
+        pub fn get(&self, key: &str) -> Option<&Value> {
+            let entries = self.as_object()?;
+
+            for entry in entries {
+                if entry.0 == key {
+                    return Some(&entry.1);
+                }
+            }
+
+            None
+        }
+    }
+
+    pub fn parse(source: &str) -> Result<Value, String> {
+        let mut chars = source.chars().peekable();
+        let value = parse_value(&mut chars)?;
+        skip_whitespace(&mut chars);
+
+        if chars.next().is_some() {
+            return Err("unexpected trailing content after theme JSON".to_string());
+        }
+
+        Ok(value)
+    }
+
+    fn parse_value<'a>(chars: &mut Peekable<Chars<'a>>) -> Result<Value, String> {
+        skip_whitespace(chars);
+
+        match chars.peek() {
+            Some(&'{') => parse_object(chars),
+            Some(&'[') => parse_array(chars),
+            Some(&'"') => parse_string(chars).map(Value::String),
+            Some(&c) => Err(format!("unexpected character '{}' in theme JSON", c)),
+            None => Err("unexpected end of theme JSON".to_string()),
+        }
+    }
+
+    fn parse_object<'a>(chars: &mut Peekable<Chars<'a>>) -> Result<Value, String> {
+        expect(chars, '{')?;
+        let mut entries = Vec::new();
+
+        skip_whitespace(chars);
+        if chars.peek() == Some(&'}') {
+            chars.next();
+            return Ok(Value::Object(entries));
+        }
+
+        loop {
+            skip_whitespace(chars);
+            let key = parse_string(chars)?;
+            skip_whitespace(chars);
+            expect(chars, ':')?;
+            let value = parse_value(chars)?;
+            entries.push((key, value));
+
+            skip_whitespace(chars);
+            match chars.next() {
+                Some(',') => continue,
+                Some('}') => break,
+                _ => return Err("expected ',' or '}' in theme JSON object".to_string()),
+            }
+        }
+
+        Ok(Value::Object(entries))
+    }
+
+    fn parse_array<'a>(chars: &mut Peekable<Chars<'a>>) -> Result<Value, String> {
+        expect(chars, '[')?;
+        let mut entries = Vec::new();
+
+        skip_whitespace(chars);
+        if chars.peek() == Some(&']') {
+            chars.next();
+            return Ok(Value::Array(entries));
+        }
+
+        loop {
+            entries.push(parse_value(chars)?);
+
+            skip_whitespace(chars);
+            match chars.next() {
+                Some(',') => continue,
+                Some(']') => break,
+                _ => return Err("expected ',' or ']' in theme JSON array".to_string()),
+            }
+        }
+
+        Ok(Value::Array(entries))
+    }
+
+    fn parse_string<'a>(chars: &mut Peekable<Chars<'a>>) -> Result<String, String> {
+        expect(chars, '"')?;
+        let mut value = String::new();
+
+        loop {
+            match chars.next() {
+                Some('"') => break,
+                Some('\\') => match chars.next() {
+                    Some('"') => value.push('"'),
+                    Some('\\') => value.push('\\'),
+                    Some('/') => value.push('/'),
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some(other) => return Err(format!("unsupported escape sequence '\\{}' in theme JSON", other)),
+                    None => return Err("unterminated escape sequence in theme JSON string".to_string()),
+                },
+                Some(c) => value.push(c),
+                None => return Err("unterminated string in theme JSON".to_string()),
+            }
+        }
+
+        Ok(value)
+    }
+
+    fn expect<'a>(chars: &mut Peekable<Chars<'a>>, expected: char) -> Result<(), String> {
+        match chars.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(format!("expected '{}' but found '{}' in theme JSON", expected, c)),
+            None => Err(format!("expected '{}' but reached end of theme JSON", expected)),
+        }
+    }
+
+    fn skip_whitespace<'a>(chars: &mut Peekable<Chars<'a>>) {
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                chars.next();
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn style(foreground: (u8, u8, u8)) -> ScopeStyle {
+        ScopeStyle{
+            foreground: RGBColor(foreground.0, foreground.1, foreground.2),
+            background: None,
+            font_style: FontStyle::default(),
+        }
+    }
+
+    #[test]
+    fn resolve_prefers_the_most_specific_matching_rule() {
+        let theme = Theme::new(vec![
+            ThemeRule{ scope: "string".to_string(), style: style((1, 1, 1)) },
+            ThemeRule{ scope: "string.quoted".to_string(), style: style((2, 2, 2)) },
+        ], style((0, 0, 0)));
+
+        let resolved = theme.resolve("string.quoted.double");
+
+        assert_eq!((resolved.foreground.0, resolved.foreground.1, resolved.foreground.2), (2, 2, 2));
+    }
+
+    #[test]
+    fn resolve_falls_back_to_the_default_style() {
+        let theme = Theme::new(vec![
+            ThemeRule{ scope: "string".to_string(), style: style((1, 1, 1)) },
+        ], style((9, 9, 9)));
+
+        let resolved = theme.resolve("comment.line");
+
+        assert_eq!((resolved.foreground.0, resolved.foreground.1, resolved.foreground.2), (9, 9, 9));
+    }
+
+    #[test]
+    fn font_style_parse_recognizes_each_independent_attribute() {
+        let font_style = FontStyle::parse("bold italic");
+
+        assert!(font_style.bold);
+        assert!(font_style.italic);
+        assert!(!font_style.underline);
+    }
+
+    #[test]
+    fn quantize_maps_to_the_nearest_palette_color() {
+        assert_eq!(quantize(RGBColor(1, 2, 3)), Color::Black);
+        assert_eq!(quantize(RGBColor(204, 1, 1)), Color::Red);
+    }
+
+    #[test]
+    fn load_parses_rules_in_order_with_their_settings() {
+        let source = r##"{
+            "default": { "foreground": "#d4d4d4" },
+            "rules": [
+                { "scope": "string", "foreground": "#ce9178" },
+                { "scope": "string.quoted", "foreground": "#ffffff", "background": "#000000", "fontStyle": "bold" }
+            ]
+        }"##;
+
+        let theme = load(source).unwrap();
+
+        assert_eq!((theme.default_style.foreground.0, theme.default_style.foreground.1, theme.default_style.foreground.2), (0xd4, 0xd4, 0xd4));
+        assert_eq!(theme.rules.len(), 2);
+
+        let resolved = theme.resolve("string.quoted.double");
+        assert_eq!((resolved.foreground.0, resolved.foreground.1, resolved.foreground.2), (0xff, 0xff, 0xff));
+        assert!(resolved.font_style.bold);
+    }
+
+    #[test]
+    fn load_rejects_a_rule_missing_its_foreground_color() {
+        let source = r##"{
+            "default": { "foreground": "#d4d4d4" },
+            "rules": [ { "scope": "string" } ]
+        }"##;
+
+        assert!(load(source).is_err());
+    }
+
+    #[test]
+    fn parse_hex_color_rejects_non_ascii_input_of_matching_byte_length() {
+        // Two 3-byte code points: 6 bytes total, but only 2 chars. A byte-length
+        // check alone would pass this through into a slice that panics.
+        assert!(parse_hex_color("\u{20ac}\u{20ac}").is_err());
+    }
+}
@@ -0,0 +1,253 @@
+use rustbox;
+use rustbox::{Color, Style};
+use view::terminal::Terminal;
+
+/// A single on-screen character cell, along with the styling it should
+/// be painted with.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Cell {
+    pub character: char,
+    pub style: Style,
+    pub foreground: Color,
+    pub background: Color,
+}
+
+impl Default for Cell {
+    fn default() -> Cell {
+        Cell{
+            character: ' ',
+            style: rustbox::RB_NORMAL,
+            foreground: Color::Default,
+            background: Color::Default,
+        }
+    }
+}
+
+/// An in-memory grid mirroring the terminal's contents. `BufferRenderer`
+/// writes into one of these instead of printing directly to the
+/// terminal, which lets the caller diff it against the previously
+/// presented buffer and redraw only what actually changed.
+pub struct ScreenBuffer {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+    dirty: bool,
+}
+
+impl ScreenBuffer {
+    pub fn new(width: usize, height: usize) -> ScreenBuffer {
+        ScreenBuffer{
+            width: width,
+            height: height,
+            cells: vec![Cell::default(); width * height],
+            dirty: true,
+        }
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, cell: Cell) {
+        if let Some(index) = self.index(x, y) {
+            self.cells[index] = cell;
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Cell {
+        self.index(x, y).map(|index| self.cells[index]).unwrap_or_default()
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Marks the entire buffer as out-of-date, forcing the next flush to
+    /// repaint every cell rather than only those that differ.
+    pub fn invalidate(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Resizes the buffer to match a new terminal size, discarding its
+    /// contents and invalidating it so the next flush repaints
+    /// everything.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        self.cells = vec![Cell::default(); width * height];
+        self.dirty = true;
+    }
+
+    fn index(&self, x: usize, y: usize) -> Option<usize> {
+        if x < self.width && y < self.height {
+            Some(y * self.width + x)
+        } else {
+            None
+        }
+    }
+}
+
+/// A contiguous stretch of a row's changed cells that share a single
+/// style/foreground/background, ready to be written with one
+/// `print_str` call instead of one per character.
+#[derive(Debug, PartialEq)]
+struct Run {
+    start: usize,
+    text: String,
+    style: Style,
+    foreground: Color,
+    background: Color,
+}
+
+/// Diffs a single row of `front` against `back` and returns the runs of
+/// changed cells that need to be written, coalescing consecutive cells
+/// that share a style/foreground/background into one run. When
+/// `repaint_everything` is set, every cell is treated as changed
+/// regardless of what `front` holds. Broken out as a pure function so
+/// it's testable without a `Terminal`.
+fn row_runs(front: &[Cell], back: &[Cell], repaint_everything: bool) -> Vec<Run> {
+    let mut runs = Vec::new();
+    let mut x = 0;
+
+    while x < back.len() {
+        let cell = back[x];
+
+        if !repaint_everything && front[x] == cell {
+            x += 1;
+            continue;
+        }
+
+        let start = x;
+        let mut text = String::new();
+
+        while x < back.len() {
+            let run_cell = back[x];
+            let changed = repaint_everything || front[x] != run_cell;
+            let matches_run_style = run_cell.style == cell.style && run_cell.foreground == cell.foreground && run_cell.background == cell.background;
+
+            if !changed || !matches_run_style {
+                break;
+            }
+
+            text.push(run_cell.character);
+            x += 1;
+        }
+
+        runs.push(Run{ start: start, text: text, style: cell.style, foreground: cell.foreground, background: cell.background });
+    }
+
+    runs
+}
+
+/// Writes only the cells that differ between `front` and `back` to
+/// `terminal`, coalescing each row's consecutive changed cells that
+/// share a style/foreground/background into a single `print_str` call,
+/// so a contiguous stretch of identically-styled text costs one
+/// cursor-move escape instead of one per character. `front` is updated
+/// to match `back` afterwards, ready for the next frame's diff.
+pub fn flush(front: &mut ScreenBuffer, back: &ScreenBuffer, terminal: &Terminal) {
+    let repaint_everything = front.dirty || front.width != back.width || front.height != back.height;
+
+    for y in 0..back.height {
+        let row_start = y * back.width;
+        let back_row = &back.cells[row_start..row_start + back.width];
+        let front_row = if repaint_everything {
+            back_row
+        } else {
+            &front.cells[row_start..row_start + back.width]
+        };
+
+        for run in row_runs(front_row, back_row, repaint_everything) {
+            terminal.print_str(run.start, y, run.style, run.foreground, run.background, &run.text);
+        }
+    }
+
+    *front = ScreenBuffer{
+        width: back.width,
+        height: back.height,
+        cells: back.cells.clone(),
+        dirty: false,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_and_get_roundtrip_a_cell() {
+        let mut buffer = ScreenBuffer::new(4, 2);
+        let cell = Cell{ character: 'a', style: rustbox::RB_BOLD, foreground: Color::Red, background: Color::Blue };
+
+        buffer.set(1, 1, cell);
+
+        assert!(buffer.get(1, 1) == cell);
+        assert!(buffer.get(0, 0) == Cell::default());
+    }
+
+    #[test]
+    fn get_out_of_bounds_returns_the_default_cell() {
+        let buffer = ScreenBuffer::new(2, 2);
+
+        assert!(buffer.get(5, 5) == Cell::default());
+    }
+
+    #[test]
+    fn set_out_of_bounds_is_ignored() {
+        let mut buffer = ScreenBuffer::new(2, 2);
+
+        buffer.set(5, 5, Cell{ character: 'x', style: rustbox::RB_NORMAL, foreground: Color::Red, background: Color::Red });
+
+        assert_eq!(buffer.width(), 2);
+        assert_eq!(buffer.height(), 2);
+    }
+
+    #[test]
+    fn resize_discards_contents_and_invalidates() {
+        let mut buffer = ScreenBuffer::new(2, 2);
+        buffer.set(0, 0, Cell{ character: 'a', style: rustbox::RB_NORMAL, foreground: Color::Red, background: Color::Red });
+
+        buffer.resize(3, 1);
+
+        assert_eq!(buffer.width(), 3);
+        assert_eq!(buffer.height(), 1);
+        assert!(buffer.get(0, 0) == Cell::default());
+    }
+
+    fn cell(character: char, foreground: Color) -> Cell {
+        Cell{ character: character, style: rustbox::RB_NORMAL, foreground: foreground, background: Color::Default }
+    }
+
+    #[test]
+    fn row_runs_is_empty_when_nothing_changed_and_not_repainting() {
+        let row = vec![cell('a', Color::Red), cell('b', Color::Red)];
+
+        let runs = row_runs(&row, &row, false);
+
+        assert!(runs.is_empty());
+    }
+
+    #[test]
+    fn row_runs_breaks_a_run_on_a_style_change() {
+        let front = vec![Cell::default(); 3];
+        let back = vec![cell('a', Color::Red), cell('b', Color::Red), cell('c', Color::Blue)];
+
+        let runs = row_runs(&front, &back, false);
+
+        assert_eq!(runs, vec![
+            Run{ start: 0, text: "ab".to_string(), style: rustbox::RB_NORMAL, foreground: Color::Red, background: Color::Default },
+            Run{ start: 2, text: "c".to_string(), style: rustbox::RB_NORMAL, foreground: Color::Blue, background: Color::Default },
+        ]);
+    }
+
+    #[test]
+    fn row_runs_repaints_every_cell_when_repaint_everything_is_set() {
+        let row = vec![cell('a', Color::Red), cell('b', Color::Red)];
+
+        let runs = row_runs(&row, &row, true);
+
+        assert_eq!(runs, vec![
+            Run{ start: 0, text: "ab".to_string(), style: rustbox::RB_NORMAL, foreground: Color::Red, background: Color::Default },
+        ]);
+    }
+}
@@ -0,0 +1,161 @@
+use regex::Regex;
+use scribe::buffer::{Buffer, Position, Range};
+
+/// Compiles a user-entered pattern against a buffer's contents and keeps
+/// track of which of the resulting matches is "current", so that
+/// `BufferRenderer` can highlight every on-screen occurrence while
+/// emphasizing the one a search command should scroll into view.
+pub struct Search {
+    current_index: Option<usize>,
+    matches: Vec<Range>,
+}
+
+impl Search {
+    pub fn new() -> Search {
+        Search{ current_index: None, matches: Vec::new() }
+    }
+
+    /// Compiles `pattern` as a regex and finds every match in `buffer`,
+    /// selecting the first one as current. Matches are returned in
+    /// buffer order, which callers rely on for incremental highlighting.
+    pub fn search(&mut self, buffer: &Buffer, pattern: &str) -> Result<(), String> {
+        let regex = Regex::new(pattern).map_err(|e| e.to_string())?;
+        self.matches = find_matches(&buffer.data(), &regex);
+        self.current_index = if self.matches.is_empty() { None } else { Some(0) };
+
+        Ok(())
+    }
+
+    pub fn clear(&mut self) {
+        self.matches.clear();
+        self.current_index = None;
+    }
+
+    pub fn matches(&self) -> &[Range] {
+        &self.matches
+    }
+
+    pub fn current_index(&self) -> Option<usize> {
+        self.current_index
+    }
+
+    pub fn current_match(&self) -> Option<&Range> {
+        self.current_index.and_then(|index| self.matches.get(index))
+    }
+
+    /// Selects the first match starting after `position`, wrapping
+    /// around to the first match in the buffer if none is found.
+    pub fn select_next(&mut self, position: Position) {
+        self.current_index = self.matches.iter()
+            .position(|range| range.start() > position)
+            .or(if self.matches.is_empty() { None } else { Some(0) });
+    }
+
+    /// Selects the last match ending before `position`, wrapping around
+    /// to the last match in the buffer if none is found.
+    pub fn select_previous(&mut self, position: Position) {
+        self.current_index = self.matches.iter()
+            .rposition(|range| range.end() < position)
+            .or(if self.matches.is_empty() { None } else { Some(self.matches.len() - 1) });
+    }
+}
+
+// Maps each regex match's byte offsets back to the line/offset positions
+// that scribe::buffer::Range is expressed in, walking the buffer's lines
+// once rather than re-scanning from the start for every match. Position's
+// offset is a *char* offset (see input/commands/cursor.rs), so every byte
+// offset handed back by the regex crate is converted via chars().count()
+// before a Position is built from it.
+fn find_matches(data: &str, regex: &Regex) -> Vec<Range> {
+    let lines: Vec<&str> = data.lines().collect();
+    let mut matches = Vec::new();
+    let mut line = 0;
+    let mut line_start_byte = 0;
+
+    for mat in regex.find_iter(data) {
+        while line + 1 < lines.len() && mat.start() >= line_start_byte + lines[line].len() + 1 {
+            line_start_byte += lines[line].len() + 1;
+            line += 1;
+        }
+        let start = Position{ line: line, offset: char_offset(lines[line], mat.start() - line_start_byte) };
+
+        let mut end_line = line;
+        let mut end_line_start_byte = line_start_byte;
+        while end_line + 1 < lines.len() && mat.end() >= end_line_start_byte + lines[end_line].len() + 1 {
+            end_line_start_byte += lines[end_line].len() + 1;
+            end_line += 1;
+        }
+        let end = Position{ line: end_line, offset: char_offset(lines[end_line], mat.end() - end_line_start_byte) };
+
+        matches.push(Range::new(start, end));
+    }
+
+    matches
+}
+
+// Converts a byte offset within `line` to a char offset, so it can be used
+// to build a scribe::buffer::Position.
+fn char_offset(line: &str, byte_offset: usize) -> usize {
+    line[..byte_offset].chars().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scribe::buffer::Buffer;
+
+    #[test]
+    fn search_finds_matches_in_buffer_order() {
+        let mut buffer = Buffer::new();
+        buffer.insert("amp\namp editor\n");
+        let mut search = Search::new();
+
+        search.search(&buffer, "amp").unwrap();
+
+        assert_eq!(search.matches().len(), 2);
+        assert_eq!(search.matches()[0].start(), Position{ line: 0, offset: 0 });
+        assert_eq!(search.matches()[1].start(), Position{ line: 1, offset: 0 });
+        assert_eq!(search.current_index(), Some(0));
+    }
+
+    #[test]
+    fn search_converts_byte_offsets_to_char_offsets_for_multi_byte_content() {
+        let mut buffer = Buffer::new();
+        buffer.insert("caf\u{e9} amp\n");
+        let mut search = Search::new();
+
+        search.search(&buffer, "amp").unwrap();
+
+        // "caf\u{e9} " is 5 chars (c, a, f, the accented e, space; the e is one
+        // char but two bytes), so the match should land on char offset 5.
+        assert_eq!(search.current_match().unwrap().start(), Position{ line: 0, offset: 5 });
+    }
+
+    #[test]
+    fn select_next_wraps_around_to_the_first_match() {
+        let mut buffer = Buffer::new();
+        buffer.insert("amp amp amp\n");
+        let mut search = Search::new();
+        search.search(&buffer, "amp").unwrap();
+
+        search.select_next(Position{ line: 0, offset: 8 });
+        assert_eq!(search.current_index(), Some(0));
+
+        search.select_next(Position{ line: 0, offset: 0 });
+        assert_eq!(search.current_index(), Some(1));
+    }
+
+    #[test]
+    fn select_previous_wraps_around_to_the_last_match() {
+        let mut buffer = Buffer::new();
+        buffer.insert("amp amp amp\n");
+        let mut search = Search::new();
+        search.search(&buffer, "amp").unwrap();
+
+        search.select_previous(Position{ line: 0, offset: 0 });
+        assert_eq!(search.current_index(), Some(2));
+
+        search.select_previous(Position{ line: 0, offset: 11 });
+        assert_eq!(search.current_index(), Some(1));
+    }
+}
@@ -0,0 +1,173 @@
+use scribe::buffer::{Buffer, Position, Range};
+use view::buffer_renderer::next_tab_stop;
+
+/// The shape a `Selection` expands to when resolved against a cursor
+/// position: a free-form span of characters, whole lines, or a
+/// rectangular column span (as in a vi-like visual-block mode).
+#[derive(Clone, Copy, PartialEq)]
+pub enum SelectionMode {
+    Character,
+    Line,
+    Block,
+}
+
+/// A marked region of a buffer, anchored at the position where the
+/// selection began and resolved relative to the buffer's current
+/// cursor position.
+pub struct Selection {
+    pub anchor: Position,
+    pub mode: SelectionMode,
+}
+
+impl Selection {
+    pub fn new(anchor: Position, mode: SelectionMode) -> Selection {
+        Selection{ anchor: anchor, mode: mode }
+    }
+
+    /// Tests whether `buffer_position` falls inside this selection, as
+    /// resolved against the buffer's current `cursor`.
+    ///
+    /// Block-mode selections can't be expressed as a single buffer-space
+    /// `Range`, because their columns are *screen* columns: a tab
+    /// partway through a line shifts everything after it out of
+    /// alignment with the raw buffer offset. `screen_position` (already
+    /// adjusted for tab expansion and the gutter) is what block mode
+    /// tests against, so its bounds have to be expressed as screen
+    /// columns too — `block_columns` (the `(left, right)` pair produced
+    /// by `block_columns()`) is that, computed once per frame by the
+    /// caller rather than per glyph. Character and line mode test the
+    /// buffer position directly, since they don't need column alignment
+    /// across lines, and ignore `block_columns`.
+    pub fn contains(&self, cursor: Position, buffer_position: Position, screen_position: Position, block_columns: Option<(usize, usize)>) -> bool {
+        match self.mode {
+            SelectionMode::Character => {
+                let (start, end) = ordered(self.anchor, cursor);
+                Range::new(start, end).includes(&buffer_position)
+            }
+            SelectionMode::Line => {
+                let (start, end) = ordered(self.anchor, cursor);
+                buffer_position.line >= start.line && buffer_position.line <= end.line
+            }
+            SelectionMode::Block => {
+                let (top, bottom) = if self.anchor.line <= cursor.line {
+                    (self.anchor.line, cursor.line)
+                } else {
+                    (cursor.line, self.anchor.line)
+                };
+
+                if buffer_position.line < top || buffer_position.line > bottom {
+                    return false;
+                }
+
+                let (left, right) = match block_columns {
+                    Some(columns) => columns,
+                    None => return false,
+                };
+
+                screen_position.offset >= left && screen_position.offset <= right
+            }
+        }
+    }
+
+    /// Resolves the left/right screen columns a Block-mode selection
+    /// spans, tab-expanding the anchor's and cursor's lines once. `None`
+    /// outside of Block mode. Rendering a buffer calls `contains` once
+    /// per visible glyph; computing this here and passing the result in
+    /// avoids re-walking both lines (and re-fetching the whole buffer's
+    /// text) on every one of those calls.
+    pub fn block_columns(&self, buffer: &Buffer, cursor: Position, gutter_width: usize, tab_width: usize) -> Option<(usize, usize)> {
+        if self.mode != SelectionMode::Block {
+            return None;
+        }
+
+        let anchor_column = screen_column(buffer, self.anchor, tab_width) + gutter_width;
+        let cursor_column = screen_column(buffer, cursor, tab_width) + gutter_width;
+
+        Some((anchor_column.min(cursor_column), anchor_column.max(cursor_column)))
+    }
+}
+
+// Resolves a buffer position to its tab-expanded screen column, by
+// walking its line from the start the same way BufferRenderer does when
+// printing it.
+fn screen_column(buffer: &Buffer, position: Position, tab_width: usize) -> usize {
+    let data = buffer.data();
+    let line = data.lines().nth(position.line).unwrap_or("");
+    let mut column = 0;
+
+    for character in line.chars().take(position.offset) {
+        if character == '\t' {
+            column = next_tab_stop(column, tab_width);
+        } else {
+            column += 1;
+        }
+    }
+
+    column
+}
+
+fn ordered(a: Position, b: Position) -> (Position, Position) {
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scribe::buffer::Buffer;
+
+    #[test]
+    fn character_mode_includes_positions_between_anchor_and_cursor() {
+        let cursor = Position{ line: 0, offset: 6 };
+        let selection = Selection::new(Position{ line: 0, offset: 2 }, SelectionMode::Character);
+
+        assert!(selection.contains(cursor, Position{ line: 0, offset: 4 }, Position{ line: 0, offset: 4 }, None));
+        assert!(!selection.contains(cursor, Position{ line: 0, offset: 8 }, Position{ line: 0, offset: 8 }, None));
+    }
+
+    #[test]
+    fn line_mode_includes_every_position_on_spanned_lines() {
+        let cursor = Position{ line: 1, offset: 0 };
+        let selection = Selection::new(Position{ line: 0, offset: 2 }, SelectionMode::Line);
+
+        assert!(selection.contains(cursor, Position{ line: 1, offset: 9 }, Position{ line: 1, offset: 9 }, None));
+        assert!(!selection.contains(cursor, Position{ line: 2, offset: 0 }, Position{ line: 2, offset: 0 }, None));
+    }
+
+    #[test]
+    fn block_mode_resolves_tab_expanded_screen_columns() {
+        // The anchor line has a leading tab, which (at a tab width of 4)
+        // pushes its second character to screen column 4, not buffer
+        // offset 1. A block selection from here to the cursor below it
+        // should bound itself using that expanded column, not the raw
+        // buffer offset.
+        let mut buffer = Buffer::new();
+        buffer.insert("\tb\nab\n");
+        let cursor = Position{ line: 1, offset: 1 };
+        buffer.cursor.move_to(cursor);
+        let gutter_width = 2;
+        let selection = Selection::new(Position{ line: 0, offset: 1 }, SelectionMode::Block);
+        let block_columns = selection.block_columns(&buffer, cursor, gutter_width, 4);
+
+        // The block spans screen columns [1, 4] (plus the gutter): the
+        // cursor's column 1 up to the anchor's tab-expanded column 4.
+        let inside_screen_position = Position{ line: 0, offset: 3 + gutter_width };
+        assert!(selection.contains(cursor, Position{ line: 0, offset: 1 }, inside_screen_position, block_columns));
+
+        // Column 5 sits just past the anchor's expanded column, so it's
+        // outside the block even though it's right next to it.
+        let outside_screen_position = Position{ line: 0, offset: 5 + gutter_width };
+        assert!(!selection.contains(cursor, Position{ line: 0, offset: 2 }, outside_screen_position, block_columns));
+    }
+
+    #[test]
+    fn block_columns_is_none_outside_block_mode() {
+        let buffer = Buffer::new();
+        let selection = Selection::new(Position{ line: 0, offset: 0 }, SelectionMode::Character);
+
+        assert!(selection.block_columns(&buffer, Position{ line: 0, offset: 0 }, 0, 4).is_none());
+    }
+}